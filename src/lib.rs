@@ -10,36 +10,83 @@ use std::hash::Hash;
 
 /// Iterates over fuzzy matches of one string against the words in another, such
 /// that the similarity is over some threshold, for example 0.3.
-pub fn find_words_iter<'n, 'h>(
-    needle: &'n str,
+pub fn find_words_iter<'h>(
+    needle: &str,
     haystack: &'h str,
     threshold: f64,
-) -> Matches<'n, 'h> {
+) -> Matches<'h> {
     static WORD_RX: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"\w+").unwrap()
     });
     let words = WORD_RX.find_iter(haystack);
     Matches {
-        needle,
+        needle: Trigrams::new(needle),
         haystack_words: words,
         threshold,
     }
 }
 
+/// Like [`find_words_iter`] but scores words with an arbitrary [`Scorer`], letting callers pick a
+/// metric better suited to their data than trigram Jaccard — for example [`JaroWinkler`] for
+/// short strings and transpositions.
+pub fn find_words_iter_with<'h, S: Scorer>(
+    scorer: S,
+    needle: &str,
+    haystack: &'h str,
+    threshold: f64,
+) -> MatchesWith<'h, S> {
+    static WORD_RX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\w+").unwrap()
+    });
+    MatchesWith {
+        scorer,
+        needle: needle.to_string(),
+        haystack_words: WORD_RX.find_iter(haystack),
+        threshold,
+    }
+}
+
+/// Iterator over fuzzy word matches scored by a [`Scorer`], produced by [`find_words_iter_with`].
+pub struct MatchesWith<'h, S: Scorer> {
+    scorer: S,
+    needle: String,
+    haystack_words: regex::Matches<'static, 'h>,
+    threshold: f64,
+}
+
+impl<'h, S: Scorer> Iterator for MatchesWith<'h, S> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for m in self.haystack_words.by_ref() {
+            let w = m.as_str();
+            if self.scorer.score(&self.needle, w) > self.threshold {
+                let m2 = Match {
+                    text: w,
+                    start: m.start(),
+                    end: m.end(),
+                };
+                return Some(m2);
+            }
+        }
+        None
+    }
+}
+
 /// Iterator over fuzzy word matches.
-pub struct Matches<'n, 'h> {
-    needle: &'n str,
+pub struct Matches<'h> {
+    needle: Trigrams,
     haystack_words: regex::Matches<'static, 'h>,
     threshold: f64,
 }
 
-impl<'n, 'h> Iterator for Matches<'n, 'h> {
+impl<'h> Iterator for Matches<'h> {
     type Item = Match<'h>;
 
     fn next(&mut self) -> Option<Self::Item> {
         for m in self.haystack_words.by_ref() {
             let w = m.as_str();
-            if similarity(self.needle, w) > self.threshold {
+            if self.needle.similarity_to(w) > self.threshold {
                 let m2 = Match {
                     text: w,
                     start: m.start(),
@@ -72,20 +119,280 @@ impl<'t> Match<'t> {
     }
 }
 
+/// Scores each candidate against `needle` with [`similarity`] and returns those scoring above
+/// `threshold`, sorted in ascending order of score so that the best match is last. This gives
+/// the clap-style "did you mean?" behavior over a discrete list of candidates (command names,
+/// enum variants, dictionary entries) rather than the words of a single haystack.
+///
+/// Borrowing clap's suggestion heuristic, a candidate that is `needle` with its
+/// whitespace-separated words reordered (for example `"bar foo"` against `"foo bar"`) has its
+/// score boosted to at least 0.9, since such a transposition is usually what the user meant even
+/// when trigram overlap is modest.
+pub fn suggestions<I, T>(needle: &str, candidates: I, threshold: f64) -> Vec<(f64, T)>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    let mut scored: Vec<(f64, T)> = candidates
+        .into_iter()
+        .map(|c| (suggestion_score(needle, c.as_ref()), c))
+        .filter(|(s, _)| *s > threshold)
+        .collect();
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scored
+}
+
+/// Returns the single best candidate for `needle`, or `None` if none scores above `threshold`.
+/// This is the top entry of [`suggestions`].
+pub fn best_match<I, T>(needle: &str, candidates: I, threshold: f64) -> Option<T>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    suggestions(needle, candidates, threshold)
+        .pop()
+        .map(|(_, c)| c)
+}
+
+/// Similarity of `needle` to `candidate`, boosted to 0.9 when `candidate` is `needle` with its
+/// words reordered.
+fn suggestion_score(needle: &str, candidate: &str) -> f64 {
+    let score = similarity(needle, candidate);
+    if is_word_swap(needle, candidate) {
+        score.max(0.9)
+    } else {
+        score
+    }
+}
+
+/// Reports whether `a` and `b` contain the same whitespace-separated words in a different order.
+fn is_word_swap(a: &str, b: &str) -> bool {
+    let mut wa: Vec<&str> = a.split_whitespace().collect();
+    let mut wb: Vec<&str> = b.split_whitespace().collect();
+    if wa.len() < 2 || wa == wb {
+        return false;
+    }
+    wa.sort_unstable();
+    wb.sort_unstable();
+    wa == wb
+}
+
 /// Returns the similarity of two strings as the Jaccard similarity of their trigram sets. The
 /// returned value is between 0.0 and 1.0, with 1.0 indicating maximum similarity.  The input
 /// strings are normalized before comparison, so it is possible to get a score of 1.0 between
 /// different strings. For example `"figaro"` and `"Figaro?"` have a similarity of
 /// 1.0.
+///
+/// This is the `n == 3` case of [`similarity_ngram`].
 #[must_use] pub fn similarity(a: &str, b: &str) -> f64 {
+    similarity_ngram(a, b, 3)
+}
+
+/// Returns the similarity of two strings as the Jaccard similarity of their n-gram sets, where
+/// each n-gram is a window of `n` consecutive characters. Like [`similarity`], the inputs are
+/// normalized before comparison and the result is between 0.0 and 1.0.
+///
+/// For `n == 3` this is identical to [`similarity`], including the Postgres-compatible quirk of
+/// dropping trigrams that end in two spaces. For any other `n` no windows are dropped, so the
+/// score is a plain Jaccard index over the n-gram sets. Smaller windows (e.g. bigrams) match
+/// better on short tokens, while larger windows favor precision on longer ones.
+#[must_use] pub fn similarity_ngram(a: &str, b: &str, n: usize) -> f64 {
+    let a = normalize(a);
+    let b = normalize(b);
+    let ta = ngrams(&a, n);
+    let tb = ngrams(&b, n);
+    jaccard(&ta, &tb)
+}
+
+/// Returns the Jaccard similarity of the n-gram sets of two token slices, where each n-gram is a
+/// window of `n` consecutive elements. This is the same Jaccard machinery as the char-based
+/// [`similarity`], but generalized to arbitrary comparable tokens: callers can slide over
+/// whitespace-split words, grapheme clusters, or any other `Hash + Eq` type rather than Unicode
+/// scalar values. Note that [`similarity`] (the `n == 3` char case) additionally drops trigrams
+/// ending in two spaces to stay Postgres-compatible, so it is not expressible as a plain n-gram
+/// Jaccard and is not built on top of this function.
+#[must_use] pub fn ngram_similarity<T: Hash + Eq + Clone>(a: &[T], b: &[T], n: usize) -> f64 {
+    let sa = slice_ngrams(a, n);
+    let sb = slice_ngrams(b, n);
+    jaccard(&sa, &sb)
+}
+
+/// Returns the set of n-gram windows over a slice of tokens.
+fn slice_ngrams<T: Hash + Eq + Clone>(tokens: &[T], n: usize) -> HashSet<Vec<T>> {
+    if n == 0 || tokens.len() < n {
+        return HashSet::new();
+    }
+    (0..=tokens.len() - n)
+        .map(|i| tokens[i..i + n].to_vec())
+        .collect()
+}
+
+/// A needle whose normalized form and trigram set have been precomputed, so that matching it
+/// against many strings does not re-normalize and rebuild them for every comparison. Build it
+/// once with [`Trigrams::new`] and call [`Trigrams::similarity_to`] against each candidate.
+#[derive(Clone, Debug)]
+pub struct Trigrams {
+    trigrams: HashSet<String>,
+}
+
+impl Trigrams {
+    /// Precomputes the normalized form and owned trigram set of `needle`.
+    #[must_use] pub fn new(needle: &str) -> Self {
+        let normalized = normalize(needle);
+        let trigrams = ngrams(&normalized, 3).into_iter().map(String::from).collect();
+        Trigrams { trigrams }
+    }
+
+    /// Returns the trigram Jaccard similarity of this needle to `other`. This is equivalent to
+    /// `similarity(needle, other)` but reuses the needle's precomputed trigrams.
+    #[must_use] pub fn similarity_to(&self, other: &str) -> f64 {
+        let other = normalize(other);
+        let ot = ngrams(&other, 3);
+        let i = ot.iter().filter(|t| self.trigrams.contains(**t)).count() as f64;
+        let u = (self.trigrams.len() + ot.len()) as f64 - i;
+        if u == 0.0 {
+            1.0
+        } else {
+            i / u
+        }
+    }
+}
+
+/// Normalizes a string for trigram comparison: non-word runs (and the string ends) become
+/// double spaces, and everything is lowercased.
+fn normalize(s: &str) -> String {
     static RX: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"^|$|\W+").unwrap()
     });
-    let a = RX.replace_all(a, "  ").to_lowercase();
-    let b = RX.replace_all(b, "  ").to_lowercase();
-    let ta = trigrams(&a);
-    let tb = trigrams(&b);
-    jaccard(&ta, &tb)
+    RX.replace_all(s, "  ").to_lowercase()
+}
+
+/// A string-similarity metric returning a value between 0.0 and 1.0, with 1.0 meaning identical.
+/// This abstracts over the different metrics so callers can pick the one that fits their data.
+pub trait Scorer {
+    /// Returns the similarity of `a` and `b`.
+    fn score(&self, a: &str, b: &str) -> f64;
+}
+
+/// Trigram Jaccard similarity, the metric used by [`similarity`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrigramScorer;
+
+impl Scorer for TrigramScorer {
+    fn score(&self, a: &str, b: &str) -> f64 {
+        similarity(a, b)
+    }
+}
+
+/// Normalized Levenshtein similarity, computed as `1 - distance / max_len`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Levenshtein;
+
+impl Scorer for Levenshtein {
+    fn score(&self, a: &str, b: &str) -> f64 {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        let max = a.len().max(b.len());
+        if max == 0 {
+            return 1.0;
+        }
+        1.0 - levenshtein(&a, &b) as f64 / max as f64
+    }
+}
+
+/// Jaro-Winkler similarity, which boosts the Jaro score for strings sharing a common prefix.
+#[derive(Clone, Copy, Debug)]
+pub struct JaroWinkler {
+    /// Prefix scaling factor, conventionally 0.1.
+    pub p: f64,
+}
+
+impl Default for JaroWinkler {
+    fn default() -> Self {
+        JaroWinkler { p: 0.1 }
+    }
+}
+
+impl Scorer for JaroWinkler {
+    fn score(&self, a: &str, b: &str) -> f64 {
+        let j = jaro(a, b);
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        // Common prefix length, capped at 4.
+        let l = a
+            .iter()
+            .zip(&b)
+            .take(4)
+            .take_while(|(x, y)| x == y)
+            .count() as f64;
+        j + l * self.p * (1.0 - j)
+    }
+}
+
+/// Returns the Jaro similarity of two strings.
+fn jaro(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    // The matching window is `floor(max_len / 2) - 1`, but floored at 1 so that adjacent
+    // transpositions in short strings (e.g. the e/h swap in "teh" vs "the") are still detected;
+    // a window of 0 would require characters to line up at the exact same index.
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1).max(1);
+    let mut a_matches = vec![false; a.len()];
+    let mut b_matches = vec![false; b.len()];
+    let mut m = 0usize;
+    for (i, ca) in a.iter().enumerate() {
+        let lo = i.saturating_sub(window);
+        let hi = (i + window + 1).min(b.len());
+        for j in lo..hi {
+            if !b_matches[j] && *ca == b[j] {
+                a_matches[i] = true;
+                b_matches[j] = true;
+                m += 1;
+                break;
+            }
+        }
+    }
+    if m == 0 {
+        return 0.0;
+    }
+    // Count transpositions among the matched characters, in order.
+    let mut t = 0usize;
+    let mut k = 0usize;
+    for (i, matched) in a_matches.iter().enumerate() {
+        if *matched {
+            while !b_matches[k] {
+                k += 1;
+            }
+            if a[i] != b[k] {
+                t += 1;
+            }
+            k += 1;
+        }
+    }
+    let t = t as f64 / 2.0;
+    let m = m as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - t) / m) / 3.0
+}
+
+/// Levenshtein edit distance between two character slices.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0usize; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j + 1] + 1).min(cur[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
 }
 
 /// Jaccard similarity between two sets.
@@ -103,14 +410,15 @@ where
     }
 }
 
-/// Returns the set of trigrams found in s, except ones ending in two spaces.
-fn trigrams(s: &str) -> HashSet<&str> {
-    // The filter is to match an idiosyncrasy of the Postgres trigram extension:
-    // it doesn't count trigrams that end with two spaces.
+/// Returns the set of n-grams found in s, each a window of `n` consecutive characters.
+fn ngrams(s: &str, n: usize) -> HashSet<&str> {
+    // For trigrams we match an idiosyncrasy of the Postgres trigram extension:
+    // it doesn't count trigrams that end with two spaces. No other window size
+    // carries that quirk, so those are a plain set of n-grams.
     let idxs = rune_indexes(s);
-    (0..idxs.len() - 3)
-        .map(|i| &s[idxs[i]..idxs[i + 3]])
-        .filter(|t| !t.ends_with("  ")).collect()
+    (0..idxs.len().saturating_sub(n))
+        .map(|i| &s[idxs[i]..idxs[i + n]])
+        .filter(|t| n != 3 || !t.ends_with("  ")).collect()
 }
 
 /// Returns a vec of all the indexes of characters within the string, plus a
@@ -221,6 +529,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ngram_sizes() {
+        // n == 3 agrees with the default similarity function.
+        assert_eq!(
+            similarity_ngram("foo", "food", 3),
+            similarity("foo", "food"),
+            "similarity_ngram with n = 3 matches similarity"
+        );
+        // Any window size scores a string against itself as 1.0.
+        for n in 2..=5 {
+            assert_eq!(
+                similarity_ngram("dancing bear", "dancing bear", n),
+                1.0,
+                "checking {}-gram self similarity",
+                n
+            );
+        }
+        // Bigrams recover more overlap than trigrams on a short token.
+        assert!(
+            similarity_ngram("color", "colour", 2) > similarity_ngram("color", "colour", 3),
+            "bigrams should match better than trigrams on a short word"
+        );
+    }
+
+    #[test]
+    fn generic_ngrams() {
+        // A slice is identical to itself under any window size.
+        let toks = ["dancing", "brown", "bear", "runs"];
+        assert_eq!(ngram_similarity(&toks, &toks, 2), 1.0);
+        // Word-level bigram similarity over whitespace-split tokens.
+        let a: Vec<&str> = "dancing brown bear".split_whitespace().collect();
+        let b: Vec<&str> = "dancing black bear".split_whitespace().collect();
+        // Bigrams: {dancing brown, brown bear} vs {dancing black, black bear}; no overlap.
+        assert_eq!(ngram_similarity(&a, &b, 2), 0.0);
+        // Disjoint token sets have zero similarity. Slices shorter than the window have no
+        // n-grams, and two empty n-gram sets score 1.0.
+        assert_eq!(ngram_similarity(&[1, 2, 3], &[4, 5, 6], 2), 0.0);
+        assert_eq!(ngram_similarity(&[1], &[1], 2), 1.0);
+    }
+
+    #[test]
+    fn scorers() {
+        // Normalized Levenshtein: two substitutions (the swapped e/h) out of three characters.
+        assert!((Levenshtein.score("teh", "the") - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(Levenshtein.score("", ""), 1.0);
+        // Jaro and Jaro-Winkler match the textbook values for martha/marhta.
+        assert!((jaro("martha", "marhta") - 0.944_444).abs() < 1e-5);
+        assert!((JaroWinkler::default().score("martha", "marhta") - 0.961_111).abs() < 1e-5);
+        assert_eq!(jaro("", ""), 1.0);
+        assert_eq!(jaro("abc", ""), 0.0);
+        // The trigram scorer agrees with the free function.
+        assert_eq!(TrigramScorer.score("foo", "food"), similarity("foo", "food"));
+    }
+
+    #[test]
+    fn finding_with_scorer() {
+        let actual: Vec<&str> = find_words_iter_with(JaroWinkler::default(), "teh", "the cat", 0.8)
+            .map(|m| m.as_str())
+            .collect();
+        assert_eq!(actual, vec!["the"]);
+    }
+
+    #[test]
+    fn precompiled_needle() {
+        let needle = Trigrams::new("dancing bear");
+        for w in &["dancing boar", "dancing bear", "unrelated", ""] {
+            assert_eq!(
+                needle.similarity_to(w),
+                similarity("dancing bear", w),
+                "precompiled similarity_to should match similarity for '{}'",
+                w
+            );
+        }
+    }
+
+    #[test]
+    fn suggesting() {
+        let candidates = vec!["stash", "status", "stats", "commit"];
+        let sugg = suggestions("statuss", candidates, 0.3);
+        // Sorted ascending, so the best match is last.
+        assert_eq!(sugg.last().unwrap().1, "status");
+        assert_eq!(best_match("statuss", vec!["stash", "status", "stats"], 0.3), Some("status"));
+        // Nothing above the threshold yields no suggestions.
+        assert!(suggestions("zzzzz", vec!["stash", "status"], 0.3).is_empty());
+    }
+
+    #[test]
+    fn word_swap_boost() {
+        let sugg = suggestions("foo bar", vec!["bar foo"], 0.3);
+        assert_eq!(sugg.len(), 1, "a reordered phrase should be suggested");
+        assert!(sugg[0].0 >= 0.9, "a word swap should be boosted to at least 0.9");
+    }
+
     #[test]
     fn finding() {
         let table = vec![